@@ -1,13 +1,15 @@
 use anyhow::{anyhow, bail, Result};
-use fantoccini::Client;
-use fantoccini::{Element, Locator};
+use fantoccini::actions::{InputSource, KeyAction, KeyActions, MouseButton, PointerAction, PointerActions};
+use fantoccini::{Client, ClientBuilder, Element, Locator};
 use futures::prelude::*;
+use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use structopt::StructOpt;
 use tokio;
-use yaml2commands::{serde_yaml::from_str, CommandType, WebCommand};
+use yaml2commands::{serde_json, serde_yaml::from_str, CommandType, DriverConfig, DriverKind, WebCommand};
 
 #[derive(Debug, StructOpt)]
 struct CmdOption {
@@ -15,38 +17,228 @@ struct CmdOption {
     input_file: PathBuf,
     #[structopt(
         long,
-        default_value = "geckodriver",
-        help = "A path specifying where the geckodriver binary is"
+        help = "A YAML file describing the WebDriver session: driver (gecko|chrome), port, headless, binary_path, capabilities"
     )]
-    geckodriver_path: String,
+    config: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "Keep running and re-run `input_file` every time it's saved, reusing the same browser session"
+    )]
+    watch: bool,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    use std::io::Read;
-    use std::process::Command;
-    let option = CmdOption::from_args();
+fn default_binary(driver: &DriverKind) -> &'static str {
+    match driver {
+        DriverKind::Gecko => "geckodriver",
+        DriverKind::Chrome => "chromedriver",
+    }
+}
+
+fn driver_process_args(config: &DriverConfig, port: u16) -> Vec<String> {
+    match config.driver {
+        DriverKind::Gecko => vec!["--port".to_string(), port.to_string()],
+        DriverKind::Chrome => vec![format!("--port={}", port)],
+    }
+}
+
+fn build_capabilities(config: &DriverConfig) -> serde_json::Map<String, serde_json::Value> {
+    let mut capabilities = config.capabilities.clone();
+    if config.headless {
+        let options_key = match config.driver {
+            DriverKind::Gecko => "moz:firefoxOptions",
+            DriverKind::Chrome => "goog:chromeOptions",
+        };
+        let options = capabilities
+            .entry(options_key.to_string())
+            .or_insert_with(|| serde_json::json!({}));
+        if let Some(options) = options.as_object_mut() {
+            let args = options
+                .entry("args".to_string())
+                .or_insert_with(|| serde_json::json!([]));
+            if let Some(args) = args.as_array_mut() {
+                args.push(serde_json::json!("--headless"));
+            }
+        }
+    }
+    capabilities
+}
 
-    let mut input_file = OpenOptions::new().read(true).open(option.input_file)?;
+fn read_commands(path: &PathBuf) -> Result<Vec<WebCommand>> {
+    use std::io::Read;
+    let mut input_file = OpenOptions::new().read(true).open(path)?;
     let mut file_contents = String::new();
     input_file.read_to_string(&mut file_contents)?;
-    let commands: Vec<WebCommand> = from_str(&file_contents)?;
+    Ok(from_str(&file_contents)?)
+}
+
+// Only the Assert* variants carry test semantics; everything else is plain automation.
+fn is_assertion(command_type: &CommandType) -> bool {
+    matches!(
+        command_type,
+        CommandType::AssertText { .. }
+            | CommandType::AssertAttribute { .. }
+            | CommandType::AssertExists
+            | CommandType::AssertNotExists
+            | CommandType::AssertElementCount { .. }
+    )
+}
 
-    let mut child = Command::new(&option.geckodriver_path).spawn()?;
-    let mut client = Client::new("http://localhost:4444").await?;
+// A command's outcome is judged by whatever it ultimately does, not by a wrapping Recursive.
+fn terminal_command_type(command: &WebCommand) -> &CommandType {
+    let mut current = &command.command_type;
+    while let CommandType::Recursive(inner) = current {
+        current = &inner.command_type;
+    }
+    current
+}
+
+#[derive(Default)]
+struct RunStats {
+    assert_passed: usize,
+    assert_failed: usize,
+    other_errors: usize,
+}
+
+// Runs every command in `commands` against `client`, printing each failure as it happens.
+// Unlike the original fire-and-forget loop this keeps going after an error instead of stopping
+// at the first one, so a dead session reports one error per remaining command.
+async fn run_script(
+    commands: &[WebCommand],
+    client: &mut Client,
+    context: &mut HashMap<String, serde_json::Value>,
+) -> RunStats {
+    let mut stats = RunStats::default();
     for command in commands {
-        if let Err(e) = run_command(&mut client, &command).await {
-            eprintln!("Error has occured: {}", e);
-            break;
+        let assertion = is_assertion(terminal_command_type(command));
+        match run_command(client, context, command).await {
+            Ok(()) => {
+                if assertion {
+                    stats.assert_passed += 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("Error has occured: {}", e);
+                if assertion {
+                    stats.assert_failed += 1;
+                } else {
+                    stats.other_errors += 1;
+                }
+            }
+        }
+    }
+    stats
+}
+
+fn print_summary(stats: &RunStats) {
+    println!(
+        "\ntest result: {}. {} assertion(s) passed; {} failed; {} total{}",
+        if stats.assert_failed == 0 { "ok" } else { "FAILED" },
+        stats.assert_passed,
+        stats.assert_failed,
+        stats.assert_passed + stats.assert_failed,
+        if stats.other_errors > 0 {
+            format!("; {} other command error(s)", stats.other_errors)
+        } else {
+            String::new()
+        }
+    );
+}
+
+async fn run_once(
+    input_file: &PathBuf,
+    client: &mut Client,
+    context: &mut HashMap<String, serde_json::Value>,
+) {
+    match read_commands(input_file) {
+        Ok(commands) => {
+            let stats = run_script(&commands, client, context).await;
+            print_summary(&stats);
+        }
+        Err(e) => eprintln!("Failed to parse {}: {}", input_file.display(), e),
+    }
+}
+
+// Re-reads and re-runs input_file every time it changes, reusing client/context across runs.
+async fn watch_and_run(
+    input_file: &PathBuf,
+    client: &mut Client,
+    context: &mut HashMap<String, serde_json::Value>,
+) -> Result<()> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if matches!(event.kind, EventKind::Modify(_)) {
+                let _ = tx.blocking_send(());
+            }
         }
+    })?;
+    watcher.watch(input_file, RecursiveMode::NonRecursive)?;
+
+    run_once(input_file, client, context).await;
+    eprintln!("Watching {} for changes...", input_file.display());
+
+    while rx.recv().await.is_some() {
+        // Debounce: drain any further events for a short window so rapid saves coalesce.
+        while tokio::time::timeout(Duration::from_millis(300), rx.recv())
+            .await
+            .is_ok()
+        {}
+        run_once(input_file, client, context).await;
+        eprintln!("Watching {} for changes...", input_file.display());
     }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    use std::process::Command;
+    let option = CmdOption::from_args();
+
+    let config: DriverConfig = match &option.config {
+        Some(path) => from_str(&std::fs::read_to_string(path)?)?,
+        None => DriverConfig::default(),
+    };
+    let port = config.port.unwrap_or(4444);
+    let binary_path = config
+        .binary_path
+        .clone()
+        .unwrap_or_else(|| default_binary(&config.driver).to_string());
+
+    let mut child = Command::new(&binary_path)
+        .args(driver_process_args(&config, port))
+        .spawn()?;
+    let mut client = ClientBuilder::native()
+        .capabilities(build_capabilities(&config))
+        .connect(&format!("http://localhost:{}", port))
+        .await?;
+
+    let mut context: HashMap<String, serde_json::Value> = HashMap::new();
+
+    if option.watch {
+        watch_and_run(&option.input_file, &mut client, &mut context).await?;
+        child.kill()?;
+        return Ok(());
+    }
+
+    let commands = read_commands(&option.input_file)?;
+    let stats = run_script(&commands, &mut client, &mut context).await;
     child.kill()?;
 
+    print_summary(&stats);
+
+    if stats.assert_failed > 0 {
+        std::process::exit(1);
+    }
+
     Ok(())
 }
 
 fn run_command<'c>(
     client: &'c mut Client,
+    context: &'c mut HashMap<String, serde_json::Value>,
     command: &'c WebCommand,
 ) -> future::BoxFuture<'c, Result<()>> {
     use std::iter::from_fn;
@@ -71,28 +263,92 @@ fn run_command<'c>(
     async move {
         stream::iter(it)
             .map(Result::Ok)
-            .try_fold((None, client), |(elem, client), command| async move {
-                do_command_detail(elem, command, client)
-                    .await
-                    .map(|e| (e, client))
-            })
+            .try_fold(
+                (None, client, context),
+                |(elem, client, context), command| async move {
+                    do_command_detail(elem, command, client, context)
+                        .await
+                        .map(|e| (e, client, context))
+                },
+            )
             .await?;
         Ok(())
     }
     .boxed()
 }
 
+// Replaces {{name}} placeholders with values from context, falling back to env vars.
+fn resolve_template(template: &str, context: &HashMap<String, serde_json::Value>) -> Result<String> {
+    let mut resolved = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        resolved.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let end = after_open
+            .find("}}")
+            .ok_or_else(|| anyhow!("unterminated `{{{{` placeholder in `{}`", template))?;
+        let key = after_open[..end].trim();
+        let value = context
+            .get(key)
+            .map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .or_else(|| std::env::var(key).ok())
+            .ok_or_else(|| anyhow!("unresolved placeholder `{{{{{}}}}}`: no such variable", key))?;
+        resolved.push_str(&value);
+        rest = &after_open[end + 2..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Maps a key name from a script (`"Ctrl"`, `"Enter"`, a literal character, ...) to the WebDriver
+/// key code fantoccini's action chains expect.
+fn key_to_char(name: &str) -> char {
+    match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => '\u{E009}',
+        "shift" => '\u{E008}',
+        "alt" => '\u{E00A}',
+        "meta" | "cmd" | "command" => '\u{E03D}',
+        "enter" | "return" => '\u{E007}',
+        "tab" => '\u{E004}',
+        "escape" | "esc" => '\u{E00C}',
+        "backspace" => '\u{E003}',
+        "delete" => '\u{E017}',
+        _ => name.chars().next().unwrap_or('\u{E000}'),
+    }
+}
+
+static SCREENSHOT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Substitutes {index}/{timestamp} so repeated captures inside a Loop don't overwrite each other.
+fn substitute_capture_placeholders(path: &str) -> String {
+    let index = SCREENSHOT_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    path.replace("{index}", &index.to_string())
+        .replace("{timestamp}", &timestamp.to_string())
+}
+
 async fn do_command_detail(
     elem: Option<Element>,
     command: &WebCommand,
     client: &mut Client,
+    context: &mut HashMap<String, serde_json::Value>,
 ) -> Result<Option<Element>> {
     use fantoccini::error::CmdError;
     use webdriver::error::ErrorStatus;
 
+    let resolved_selector = command
+        .selector
+        .as_ref()
+        .map(|s| resolve_template(s, context))
+        .transpose()?;
     let get_selector = || -> Result<&String> {
-        command
-            .selector
+        resolved_selector
             .as_ref()
             .ok_or_else(|| anyhow!("A command needs a selector string"))
     };
@@ -101,14 +357,14 @@ async fn do_command_detail(
     match &command.command_type {
         // Command types which don't need a element
         CommandType::GoTo(url) => {
-            client.goto(url).await?;
+            client.goto(&resolve_template(url, context)?).await?;
             Ok(elem)
         }
         CommandType::Loop(commands) => {
             loop {
                 let mut result = Ok(());
                 for command in commands {
-                    result = run_command(client, command).await;
+                    result = run_command(client, context, command).await;
                     if result.is_err() {
                         break;
                     }
@@ -169,11 +425,99 @@ async fn do_command_detail(
             eprintln!("{}", client.source().await?);
             Ok(elem)
         }
+        CommandType::Screenshot { path } => {
+            let resolved_path = substitute_capture_placeholders(&resolve_template(path, context)?);
+            let png = if get_selector().is_ok() {
+                let mut target = if let Some(ref mut e) = elem {
+                    e.find(get_next_locator()?).await?
+                } else {
+                    client.find(get_next_locator()?).await?
+                };
+                target.screenshot().await?
+            } else {
+                client.screenshot().await?
+            };
+            std::fs::write(&resolved_path, png)?;
+            Ok(elem)
+        }
         CommandType::Wait => {
             let locator = get_next_locator()?;
             client.wait_for_find(locator).await?;
             Ok(elem)
         }
+        CommandType::AssertExists => {
+            let found = if let Some(ref mut e) = elem {
+                e.find(get_next_locator()?).await.is_ok()
+            } else {
+                client.find(get_next_locator()?).await.is_ok()
+            };
+            if found {
+                Ok(elem)
+            } else {
+                bail!(
+                    "assertion failed: expected element matching `{}` to exist, but it was not found",
+                    get_selector()?
+                )
+            }
+        }
+        CommandType::AssertNotExists => {
+            let found = if let Some(ref mut e) = elem {
+                e.find(get_next_locator()?).await.is_ok()
+            } else {
+                client.find(get_next_locator()?).await.is_ok()
+            };
+            if found {
+                bail!(
+                    "assertion failed: expected element matching `{}` to not exist, but it was found",
+                    get_selector()?
+                )
+            } else {
+                Ok(elem)
+            }
+        }
+        CommandType::KeyCombo(keys) => {
+            let codes: Vec<char> = keys.iter().map(|k| key_to_char(k)).collect();
+            let mut actions = KeyActions::new("keyboard".to_string());
+            for code in &codes {
+                actions = actions.then(KeyAction::Down { value: *code });
+            }
+            for code in codes.iter().rev() {
+                actions = actions.then(KeyAction::Up { value: *code });
+            }
+            client.perform_actions(actions).await?;
+            Ok(elem)
+        }
+        CommandType::PressKey(key) => {
+            let code = key_to_char(key);
+            let actions = KeyActions::new("keyboard".to_string())
+                .then(KeyAction::Down { value: code })
+                .then(KeyAction::Up { value: code });
+            client.perform_actions(actions).await?;
+            Ok(elem)
+        }
+        CommandType::SaveJson { path } => {
+            let contents = serde_json::to_string_pretty(context)?;
+            std::fs::write(path, contents)?;
+            Ok(elem)
+        }
+        CommandType::AssertElementCount { count } => {
+            let matches = if let Some(ref mut e) = elem {
+                e.find_all(get_next_locator()?).await?
+            } else {
+                client.find_all(get_next_locator()?).await?
+            };
+            let actual = matches.len();
+            if actual == *count {
+                Ok(elem)
+            } else {
+                bail!(
+                    "assertion failed: expected {} element(s) matching `{}`, found {}",
+                    count,
+                    get_selector()?,
+                    actual
+                )
+            }
+        }
         // Handle command types which need a element.
         _ => {
             let locator = get_next_locator()?;
@@ -221,11 +565,84 @@ async fn do_command_detail(
                     Ok(None)
                 }
                 CommandType::Input(s) => {
-                    new_elem.send_keys(s).await?;
+                    new_elem.send_keys(&resolve_template(s, context)?).await?;
                     Ok(None)
                 }
                 CommandType::Recursive(_) => Ok(Some(new_elem)),
                 CommandType::Check => Ok(None),
+                CommandType::Hover => {
+                    let actions = PointerActions::new("mouse".to_string()).then(
+                        PointerAction::MoveToElement {
+                            element: new_elem.clone(),
+                            duration: None,
+                            x: 0,
+                            y: 0,
+                        },
+                    );
+                    client.perform_actions(actions).await?;
+                    Ok(None)
+                }
+                CommandType::DragAndDrop { target_selector } => {
+                    let target = client
+                        .find(Locator::Css(&resolve_template(target_selector, context)?))
+                        .await?;
+                    let actions = PointerActions::new("mouse".to_string())
+                        .then(PointerAction::MoveToElement {
+                            element: new_elem.clone(),
+                            duration: None,
+                            x: 0,
+                            y: 0,
+                        })
+                        .then(PointerAction::Down {
+                            button: MouseButton::Left,
+                        })
+                        .then(PointerAction::MoveToElement {
+                            element: target,
+                            duration: None,
+                            x: 0,
+                            y: 0,
+                        })
+                        .then(PointerAction::Up {
+                            button: MouseButton::Left,
+                        });
+                    client.perform_actions(actions).await?;
+                    Ok(None)
+                }
+                CommandType::Extract { name, attribute } => {
+                    let value = match attribute {
+                        Some(attr) => new_elem.attr(attr).await?,
+                        None => Some(new_elem.text().await?),
+                    };
+                    context.insert(
+                        name.clone(),
+                        value.map_or(serde_json::Value::Null, serde_json::Value::String),
+                    );
+                    Ok(None)
+                }
+                CommandType::AssertText { expected } => {
+                    let actual = new_elem.text().await?;
+                    if &actual == expected {
+                        Ok(None)
+                    } else {
+                        bail!(
+                            "assertion failed: text mismatch\n  expected: {:?}\n  actual:   {:?}",
+                            expected,
+                            actual
+                        )
+                    }
+                }
+                CommandType::AssertAttribute { name, expected } => {
+                    match new_elem.attr(name).await? {
+                        Some(actual) if &actual == expected => Ok(None),
+                        Some(actual) => bail!(
+                            "assertion failed: attribute `{}` mismatch\n  expected: {:?}\n  actual:   {:?}",
+                            name,
+                            expected,
+                            actual
+                        ),
+                        None => bail!("assertion failed: attribute `{}` not present on element", name),
+                    }
+                }
                 _ => unreachable!(),
             }
         }
@@ -1,5 +1,6 @@
 use serde::{Serialize, Deserialize};
 pub use serde_yaml;
+pub use serde_json;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub enum CommandType {
@@ -16,6 +17,18 @@ pub enum CommandType {
     Recursive(Box<WebCommand>),
     ScrollIntoView,
     ChangeWindowSize{width: u32, height: u32},
+    AssertText{expected: String},
+    AssertAttribute{name: String, expected: String},
+    AssertExists,
+    AssertNotExists,
+    AssertElementCount{count: usize},
+    Extract{name: String, attribute: Option<String>},
+    SaveJson{path: String},
+    KeyCombo(Vec<String>),
+    PressKey(String),
+    Hover,
+    DragAndDrop{target_selector: String},
+    Screenshot{path: String},
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -24,6 +37,31 @@ pub struct WebCommand {
     pub command_type: CommandType,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DriverKind {
+    Gecko,
+    Chrome,
+}
+
+impl Default for DriverKind {
+    fn default() -> Self {
+        DriverKind::Gecko
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct DriverConfig {
+    #[serde(default)]
+    pub driver: DriverKind,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub headless: bool,
+    pub binary_path: Option<String>,
+    #[serde(default)]
+    pub capabilities: serde_json::Map<String, serde_json::Value>,
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -85,6 +123,90 @@ mod tests {
         assert_eq!(to_string(&commands).unwrap(), expected_str);
     }
 
+    #[test]
+    fn serialize_assert_commands() {
+        let expected_str =
+"---
+- AssertExists
+- AssertNotExists
+- AssertElementCount:
+    count: 3
+- AssertText:
+    expected: hello
+- AssertAttribute:
+    name: href
+    expected: \"https://google.com\"";
+        let command_types = vec![
+            CommandType::AssertExists,
+            CommandType::AssertNotExists,
+            CommandType::AssertElementCount{count: 3},
+            CommandType::AssertText{expected: "hello".into()},
+            CommandType::AssertAttribute{name: "href".into(), expected: "https://google.com".into()},
+        ];
+        assert_eq!(to_string(&command_types).unwrap(), expected_str);
+    }
+
+    #[test]
+    fn deserialize_driver_config() {
+        let input_str =
+"---
+driver: chrome
+port: 9515
+headless: true
+binary_path: /usr/bin/chromedriver
+capabilities:
+  acceptInsecureCerts: true";
+        let mut capabilities = serde_json::Map::new();
+        capabilities.insert("acceptInsecureCerts".into(), serde_json::Value::Bool(true));
+        let config = DriverConfig {
+            driver: DriverKind::Chrome,
+            port: Some(9515),
+            headless: true,
+            binary_path: Some("/usr/bin/chromedriver".into()),
+            capabilities,
+        };
+        assert_eq!(from_str::<DriverConfig>(input_str).unwrap(), config);
+    }
+
+    #[test]
+    fn deserialize_driver_config_defaults() {
+        let input_str = "---\n{}";
+        assert_eq!(
+            from_str::<DriverConfig>(input_str).unwrap(),
+            DriverConfig::default()
+        );
+    }
+
+    #[test]
+    fn serialize_action_commands() {
+        let expected_str =
+"---
+- KeyCombo:
+    - Ctrl
+    - a
+- PressKey: Enter
+- Hover
+- DragAndDrop:
+    target_selector: \"ul#list li:last-child\"";
+        let command_types = vec![
+            CommandType::KeyCombo(vec!["Ctrl".into(), "a".into()]),
+            CommandType::PressKey("Enter".into()),
+            CommandType::Hover,
+            CommandType::DragAndDrop{target_selector: "ul#list li:last-child".into()},
+        ];
+        assert_eq!(to_string(&command_types).unwrap(), expected_str);
+    }
+
+    #[test]
+    fn serialize_screenshot_command() {
+        let expected_str =
+"---
+- Screenshot:
+    path: \"out/shot-{index}.png\"";
+        let command_types = vec![CommandType::Screenshot{path: "out/shot-{index}.png".into()}];
+        assert_eq!(to_string(&command_types).unwrap(), expected_str);
+    }
+
     #[test]
     fn deserialize_test() {
         let input_str =